@@ -0,0 +1,94 @@
+mod common;
+
+use std::thread;
+use std::time::Instant;
+
+use common::CountingAlloc;
+use euler_rust::collatz::{longest_chain, Collatz};
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// The longest chain starting below `limit`, as `(start, length)`.
+fn longest(mut collatz: Collatz, limit: u128) -> (u128, u128) {
+    (1..limit)
+        .map(|i| (i, collatz.collatz(i)))
+        .max_by_key(|&(i, len)| (len, std::cmp::Reverse(i)))
+        .unwrap()
+}
+
+fn measure(run: impl FnOnce() -> (u128, u128)) -> ((u128, u128), f64, usize) {
+    common::reset_peak();
+    let baseline = common::current();
+    let start = Instant::now();
+    let result = run();
+    let elapsed = start.elapsed().as_secs_f64();
+    (result, elapsed, common::peak_since(baseline))
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>10} {:>6} {:>10} {:>12} {:>10} {:>12}",
+        "limit", "start", "len", "dense s", "dense mem", "map s", "map mem"
+    );
+    for &limit in &[100_000, 1_000_000, 10_000_000] {
+        let (best, time, mem) = measure(|| longest(Collatz::with_capacity(limit), limit as u128));
+        let (map_best, map_time, map_mem) = measure(|| longest(Collatz::new(), limit as u128));
+        assert_eq!(best, map_best);
+        println!(
+            "{:>10} {:>10} {:>6} {:>10.3} {:>12} {:>10.3} {:>12}",
+            limit,
+            best.0,
+            best.1,
+            time,
+            common::format_bytes(mem),
+            map_time,
+            common::format_bytes(map_mem)
+        );
+    }
+
+    // COLLATZ_THREADS overrides the detected core count, e.g. to check the
+    // overhead of oversubscribing a small machine.
+    let available = thread::available_parallelism().map_or(1, |n| n.get());
+    let cores = std::env::var("COLLATZ_THREADS")
+        .ok()
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or(available);
+    let mut thread_counts = vec![1];
+    while thread_counts.last().unwrap() * 2 <= cores {
+        thread_counts.push(thread_counts.last().unwrap() * 2);
+    }
+    if *thread_counts.last().unwrap() != cores {
+        thread_counts.push(cores);
+    }
+    println!();
+    // Speedups only mean something when the threads get their own cores.
+    println!("available cores: {}, threads up to: {}", available, cores);
+    println!(
+        "{:>10} {:>10} {:>8} {:>10} {:>6} {:>10} {:>12} {:>8}",
+        "low", "limit", "threads", "start", "len", "time s", "mem", "speedup"
+    );
+    for &(low, limit) in &[
+        (1, 1_000_000),
+        (1, 10_000_000),
+        (9_999_000, 10_000_000),
+        (1, 100_000_000),
+    ] {
+        let mut single = None;
+        for &threads in &thread_counts {
+            let (best, time, mem) = measure(|| longest_chain(low, limit, threads).unwrap());
+            let single = *single.get_or_insert(time);
+            println!(
+                "{:>10} {:>10} {:>8} {:>10} {:>6} {:>10.3} {:>12} {:>8.2}",
+                low,
+                limit,
+                threads,
+                best.0,
+                best.1,
+                time,
+                common::format_bytes(mem),
+                single / time
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::time::Instant;
+
+use common::CountingAlloc;
+use euler_rust::primes::PrimeSieve;
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// The sieve as it was before segmentation: one `bool` per integer.
+fn byte_sieve_count(limit: usize) -> usize {
+    let mut sieve = vec![true; limit + 1];
+    let mut count = 0;
+    for i in 2..=limit {
+        if sieve[i] {
+            count += 1;
+            for j in (i * i..=limit).step_by(i) {
+                sieve[j] = false;
+            }
+        }
+    }
+    count
+}
+
+fn measure(run: impl FnOnce() -> usize) -> (usize, f64, usize) {
+    common::reset_peak();
+    let baseline = common::current();
+    let start = Instant::now();
+    let result = run();
+    let elapsed = start.elapsed().as_secs_f64();
+    (result, elapsed, common::peak_since(baseline))
+}
+
+fn main() {
+    println!(
+        "{:>14} {:>10} {:>10} {:>12} {:>10} {:>12}",
+        "limit", "pi(limit)", "sieve s", "sieve mem", "bytes s", "bytes mem"
+    );
+    for &limit in &[1_000_000, 10_000_000, 100_000_000, 1_000_000_000] {
+        let (count, time, mem) = measure(|| PrimeSieve::new(limit).count());
+        let (byte_time, byte_mem) = match limit <= 100_000_000 {
+            true => {
+                let (byte_count, byte_time, byte_mem) = measure(|| byte_sieve_count(limit));
+                assert_eq!(count, byte_count);
+                (format!("{:.3}", byte_time), common::format_bytes(byte_mem))
+            }
+            false => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:>14} {:>10} {:>10.3} {:>12} {:>10} {:>12}",
+            limit,
+            count,
+            time,
+            common::format_bytes(mem),
+            byte_time,
+            byte_mem
+        );
+    }
+}
@@ -1,49 +1,427 @@
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::thread;
+
+/// Summary of the trajectory from `n` down to 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollatzStats {
+    /// Number of values on the path, counting `n` and 1.
+    pub length: u128,
+    /// Largest value on the path.
+    pub peak: u128,
+    /// Steps taken until the path first drops below `n`, or `None` for 1.
+    pub stopping_time: Option<u64>,
+    pub odd_steps: u64,
+    pub even_steps: u64,
+}
 
 pub struct Collatz {
-    cache: HashMap<u128, u128>,
+    cache: Cache,
+}
+
+/// Chain lengths seen so far. `Sparse` keeps every value visited, however
+/// large; `Dense` only keeps values below its table's length and walks
+/// through anything above it.
+enum Cache {
+    Sparse(HashMap<u128, u128>),
+    Dense { lengths: Vec<u32>, filled: usize },
+}
+
+impl Cache {
+    fn len(&self) -> usize {
+        match self {
+            Cache::Sparse(cache) => cache.len(),
+            Cache::Dense { filled, .. } => *filled,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl Collatz {
     pub fn new() -> Collatz {
         Collatz {
-            cache: HashMap::new(),
+            cache: Cache::Sparse(HashMap::new()),
+        }
+    }
+
+    /// A collatz cache holding the chain lengths of values below `bound`
+    /// in a flat table, four bytes per value.
+    pub fn with_capacity(bound: usize) -> Collatz {
+        Collatz {
+            cache: Cache::Dense {
+                lengths: vec![0; bound],
+                filled: 0,
+            },
         }
     }
 
+    /// The number of values whose chain length is cached.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
     pub fn collatz(&mut self, n: u128) -> u128 {
-        let mut to_do = vec![n];
-        while !to_do.is_empty() {
-            match to_do.pop() {
-                Some(1) => {
-                    self.cache.insert(1, 1);
-                }
-                Some(val) => {
-                    let next = collatz(&val);
-                    match &self.cache.get(&next) {
-                        Some(&dist) => {
-                            self.cache.insert(val, dist + 1);
-                        }
-                        None => {
-                            to_do.push(val);
-                            to_do.push(next);
-                        }
+        match &mut self.cache {
+            Cache::Sparse(cache) => sparse_collatz(cache, n),
+            Cache::Dense { lengths, filled } => dense_collatz(lengths, filled, n),
+        }
+    }
+
+    /// The full `CollatzStats` for `n`. The length comes from (and fills) the
+    /// cache; the rest needs one walk along the path.
+    pub fn stats(&mut self, n: u128) -> CollatzStats {
+        let length = self.collatz(n);
+        let mut peak = n;
+        let mut stopping_time = None;
+        let mut odd_steps = 0;
+        for (step, val) in trajectory(n).enumerate() {
+            peak = peak.max(val);
+            if stopping_time.is_none() && val < n {
+                stopping_time = Some(step as u64);
+            }
+            if val != 1 && !val.is_multiple_of(2) {
+                odd_steps += 1;
+            }
+        }
+        CollatzStats {
+            length,
+            peak,
+            stopping_time,
+            odd_steps,
+            even_steps: length as u64 - 1 - odd_steps,
+        }
+    }
+}
+
+/// The values from `n` down to 1, both included.
+pub fn trajectory(n: u128) -> Trajectory {
+    Trajectory { next: Some(n) }
+}
+
+pub struct Trajectory {
+    next: Option<u128>,
+}
+
+impl Iterator for Trajectory {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let val = self.next?;
+        self.next = match val {
+            1 => None,
+            _ => Some(collatz(&val)),
+        };
+        Some(val)
+    }
+}
+
+impl Default for Collatz {
+    fn default() -> Self {
+        Collatz::new()
+    }
+}
+
+fn sparse_collatz(cache: &mut HashMap<u128, u128>, n: u128) -> u128 {
+    let mut to_do = vec![n];
+    while !to_do.is_empty() {
+        match to_do.pop() {
+            Some(1) => {
+                cache.insert(1, 1);
+            }
+            Some(val) => {
+                let next = collatz(&val);
+                match &cache.get(&next) {
+                    Some(&dist) => {
+                        cache.insert(val, dist + 1);
+                    }
+                    None => {
+                        to_do.push(val);
+                        to_do.push(next);
                     }
                 }
-                _ => panic!("dont happen pls"),
             }
+            _ => panic!("dont happen pls"),
+        }
+    }
+    *cache.get(&n).unwrap()
+}
+
+/// Walks from `n` until it reaches 1 or a value already in the table, then
+/// fills in the table for every value on the way that fits in it.
+fn dense_collatz(lengths: &mut [u32], filled: &mut usize, n: u128) -> u128 {
+    let cached = |val: u128| match val < lengths.len() as u128 {
+        true => lengths[val as usize],
+        false => 0,
+    };
+    let mut path = Vec::new();
+    let mut val = n;
+    let mut dist = cached(val);
+    while dist == 0 && val != 1 {
+        path.push(val);
+        val = collatz(&val);
+        dist = cached(val);
+    }
+    let mut dist = dist.max(1);
+    if val == 1 && lengths.len() > 1 && lengths[1] == 0 {
+        lengths[1] = 1;
+        *filled += 1;
+    }
+    for &val in path.iter().rev() {
+        dist += 1;
+        if val < lengths.len() as u128 {
+            lengths[val as usize] = dist;
+            *filled += 1;
         }
-        *self.cache.get(&n).unwrap()
     }
+    dist as u128
+}
+
+/// Largest table `longest_chain` shares between its threads, 64 MiB.
+const SHARED_TABLE_LIMIT: u128 = 1 << 24;
+
+/// Smallest table `longest_chain` bothers with, however narrow the range.
+const SHARED_TABLE_MIN: u128 = 1 << 16;
+
+/// Starts handed to a thread at a time by `longest_chain`.
+const CHUNK_SIZE: u128 = 1 << 14;
+
+/// The start in `low..high` with the longest chain, as `(start, length)`,
+/// preferring the smaller start on ties. `threads` workers take chunks of
+/// the range in turn from a shared counter. They also share one table of
+/// chain lengths, which every walk fills in as it goes. The table covers
+/// values below `min(high, max(4 (high - low), 2^16), 2^24)`, so a narrow
+/// window far from 1 does not pay for a table starting at 1.
+pub fn longest_chain(low: u128, high: u128, threads: usize) -> Option<(u128, u128)> {
+    assert!(low > 0, "0 has no collatz chain");
+    assert!(threads > 0, "need at least one thread");
+    if low >= high {
+        return None;
+    }
+    let bound = (4 * (high - low))
+        .max(SHARED_TABLE_MIN)
+        .min(high)
+        .clamp(2, SHARED_TABLE_LIMIT);
+    let table: Vec<AtomicU32> = (0..bound).map(|_| AtomicU32::new(0)).collect();
+    let table = &table;
+    let chunks = (high - low).div_ceil(CHUNK_SIZE) as usize;
+    let next_chunk = &AtomicUsize::new(0);
+
+    let search = move || {
+        let mut best: Option<(u128, u128)> = None;
+        let mut path = Vec::new();
+        loop {
+            let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+            if chunk >= chunks {
+                return best;
+            }
+            let start = low + chunk as u128 * CHUNK_SIZE;
+            for n in start..(start + CHUNK_SIZE).min(high) {
+                let length = shared_collatz(table, &mut path, n);
+                if best.is_none_or(|(_, best_length)| length > best_length) {
+                    best = Some((n, length));
+                }
+            }
+        }
+    };
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(search)).collect();
+        workers
+            .into_iter()
+            .filter_map(|worker| worker.join().unwrap())
+            .max_by_key(|&(n, length)| (length, std::cmp::Reverse(n)))
+    })
+}
+
+/// `dense_collatz` over a table shared between threads. A zero entry just
+/// means nobody has got there yet, so the walk carries on past it; any
+/// two threads that store the same entry store the same length. Only the
+/// values on the path that fit in the table are remembered, along with how
+/// many steps in they were.
+fn shared_collatz(table: &[AtomicU32], path: &mut Vec<(usize, u32)>, n: u128) -> u128 {
+    path.clear();
+    let mut val = n;
+    let mut steps = 0;
+    let dist = loop {
+        while val >= table.len() as u128 {
+            val = collatz(&val);
+            steps += 1;
+        }
+        let dist = table[val as usize].load(Ordering::Relaxed);
+        if dist != 0 {
+            break dist;
+        }
+        if val == 1 {
+            table[1].store(1, Ordering::Relaxed);
+            break 1;
+        }
+        path.push((val as usize, steps));
+        val = collatz(&val);
+        steps += 1;
+    };
+    for &(val, step) in path.iter() {
+        table[val].store(dist + steps - step, Ordering::Relaxed);
+    }
+    (dist + steps) as u128
 }
 
 fn collatz(n: &u128) -> u128 {
-    match n % 2 == 0 {
+    match n.is_multiple_of(2) {
         true => n / 2,
         false => 3 * n + 1,
     }
 }
 
+/// One residue class of a `GeneralizedCollatz` map: `n -> (mul * n + add) / div`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffineBranch {
+    pub mul: u128,
+    pub add: u128,
+    pub div: u128,
+}
+
+impl AffineBranch {
+    pub fn new(mul: u128, add: u128, div: u128) -> AffineBranch {
+        AffineBranch { mul, add, div }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollatzError {
+    /// The map was given no branches at all.
+    NoBranches,
+    /// The branch for this residue does not send every value in its class
+    /// to an integer.
+    InvalidBranch(usize),
+    /// Stepping from this value overflows `u128`.
+    Overflow(u128),
+}
+
+impl fmt::Display for CollatzError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollatzError::NoBranches => write!(f, "map needs at least one branch"),
+            CollatzError::InvalidBranch(r) => {
+                write!(f, "branch for residue {} does not map to integers", r)
+            }
+            CollatzError::Overflow(n) => write!(f, "stepping from {} overflows u128", n),
+        }
+    }
+}
+
+impl Error for CollatzError {}
+
+/// Where the path from a start value ends up: after `tail` steps it enters
+/// `cycle`, which is listed from its smallest element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orbit {
+    pub tail: u64,
+    pub cycle: Vec<u128>,
+}
+
+impl Orbit {
+    /// The smallest value on the cycle, which identifies it.
+    pub fn cycle_min(&self) -> u128 {
+        self.cycle[0]
+    }
+}
+
+/// A Collatz-like map with one affine branch per residue class modulo the
+/// number of branches, such as `n / 2` or `5n + 1`.
+pub struct GeneralizedCollatz {
+    branches: Vec<AffineBranch>,
+}
+
+impl GeneralizedCollatz {
+    /// Branch `r` applies to `n = r (mod branches.len())`. Each branch must
+    /// send every member of its class to an integer.
+    pub fn new(branches: Vec<AffineBranch>) -> Result<GeneralizedCollatz, CollatzError> {
+        if branches.is_empty() {
+            return Err(CollatzError::NoBranches);
+        }
+        let modulus = branches.len() as u128;
+        for (r, branch) in branches.iter().enumerate() {
+            let integral = branch.div != 0
+                && branch
+                    .mul
+                    .checked_mul(r as u128)
+                    .and_then(|v| v.checked_add(branch.add))
+                    .is_some_and(|v| v.is_multiple_of(branch.div))
+                && branch
+                    .mul
+                    .checked_mul(modulus)
+                    .is_some_and(|v| v.is_multiple_of(branch.div));
+            if !integral {
+                return Err(CollatzError::InvalidBranch(r));
+            }
+        }
+        Ok(GeneralizedCollatz { branches })
+    }
+
+    /// `n / 2` for even `n`, `qn + 1` for odd `n`; `q = 3` is the usual map.
+    pub fn qx_plus_one(q: u128) -> GeneralizedCollatz {
+        GeneralizedCollatz::new(vec![AffineBranch::new(1, 0, 2), AffineBranch::new(q, 1, 1)])
+            .unwrap()
+    }
+
+    pub fn step(&self, n: u128) -> Result<u128, CollatzError> {
+        let branch = &self.branches[(n % self.branches.len() as u128) as usize];
+        branch
+            .mul
+            .checked_mul(n)
+            .and_then(|v| v.checked_add(branch.add))
+            .map(|v| v / branch.div)
+            .ok_or(CollatzError::Overflow(n))
+    }
+
+    /// Follows `start` until it repeats, using Brent's cycle detection, so
+    /// only a couple of values are held at once. Diverging paths end in
+    /// `CollatzError::Overflow`.
+    pub fn orbit(&self, start: u128) -> Result<Orbit, CollatzError> {
+        let mut power: u64 = 1;
+        let mut lambda: u64 = 1;
+        let mut tortoise = start;
+        let mut hare = self.step(start)?;
+        while tortoise != hare {
+            if power == lambda {
+                tortoise = hare;
+                power *= 2;
+                lambda = 0;
+            }
+            hare = self.step(hare)?;
+            lambda += 1;
+        }
+
+        let mut tortoise = start;
+        let mut hare = start;
+        for _ in 0..lambda {
+            hare = self.step(hare)?;
+        }
+        let mut tail = 0;
+        while tortoise != hare {
+            tortoise = self.step(tortoise)?;
+            hare = self.step(hare)?;
+            tail += 1;
+        }
+
+        let mut cycle = vec![tortoise];
+        for _ in 1..lambda {
+            cycle.push(self.step(*cycle.last().unwrap())?);
+        }
+        let min_at = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+        cycle.rotate_left(min_at);
+        Ok(Orbit { tail, cycle })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,10 +434,118 @@ mod tests {
         let expected = 10;
         let result = c.collatz(13);
         assert_eq!(expected, result);
-        assert!(c.cache.len() > 0);
+        assert!(!c.cache.is_empty());
 
         let expected = 26;
         let result = c.collatz(100);
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn follows_trajectories() {
+        let path: Vec<u128> = trajectory(6).collect();
+        assert_eq!(path, vec![6, 3, 10, 5, 16, 8, 4, 2, 1]);
+        assert_eq!(trajectory(1).collect::<Vec<_>>(), vec![1]);
+
+        let mut c = Collatz::with_capacity(100);
+        assert_eq!(
+            c.stats(27),
+            CollatzStats {
+                length: 112,
+                peak: 9_232,
+                stopping_time: Some(96),
+                odd_steps: 41,
+                even_steps: 70,
+            }
+        );
+        assert_eq!(c.stats(1).stopping_time, None);
+        assert_eq!(c.stats(6).stopping_time, Some(1));
+        for n in 1..1_000 {
+            let stats = c.stats(n);
+            assert_eq!(stats.length, trajectory(n).count() as u128);
+            assert_eq!(stats.peak, trajectory(n).max().unwrap());
+        }
+    }
+
+    #[test]
+    fn finds_cycles_of_generalised_maps() {
+        let three = GeneralizedCollatz::qx_plus_one(3);
+        let orbit = three.orbit(27).unwrap();
+        assert_eq!(orbit.cycle, vec![1, 4, 2]);
+        assert_eq!(orbit.tail, 109);
+        assert_eq!(three.orbit(0).unwrap().cycle, vec![0]);
+        // Every path enters the cycle at 4, two steps before reaching 1.
+        for n in (3..1_000).filter(|&n| n != 4) {
+            assert_eq!(
+                three.orbit(n).unwrap().tail + 3,
+                trajectory(n).count() as u64
+            );
+        }
+
+        let five = GeneralizedCollatz::qx_plus_one(5);
+        assert_eq!(five.orbit(1).unwrap().cycle, vec![1, 6, 3, 16, 8, 4, 2]);
+        assert_eq!(
+            five.orbit(13).unwrap().cycle,
+            vec![13, 66, 33, 166, 83, 416, 208, 104, 52, 26]
+        );
+        assert_eq!(five.orbit(34).unwrap().cycle_min(), 17);
+        assert_eq!(five.orbit(26).unwrap().tail, 0);
+        assert_eq!(five.orbit(5).unwrap().cycle_min(), 13);
+        assert_eq!(five.orbit(5).unwrap().tail, 1);
+        assert!(matches!(five.orbit(7), Err(CollatzError::Overflow(_))));
+
+        // n / 3, (4n + 2) / 3 and (4n + 1) / 3 on the classes 0, 1, 2 (mod 3).
+        let branches = vec![
+            AffineBranch::new(1, 0, 3),
+            AffineBranch::new(4, 2, 3),
+            AffineBranch::new(4, 1, 3),
+        ];
+        let map = GeneralizedCollatz::new(branches).unwrap();
+        assert_eq!(map.step(9), Ok(3));
+        assert_eq!(map.step(4), Ok(6));
+        assert_eq!(map.step(5), Ok(7));
+
+        let broken = vec![AffineBranch::new(1, 0, 2), AffineBranch::new(3, 0, 2)];
+        assert_eq!(
+            GeneralizedCollatz::new(broken).err(),
+            Some(CollatzError::InvalidBranch(1))
+        );
+        assert_eq!(
+            GeneralizedCollatz::new(vec![]).err(),
+            Some(CollatzError::NoBranches)
+        );
+    }
+
+    #[test]
+    fn finds_longest_chain_in_parallel() {
+        assert_eq!(longest_chain(1, 1_000_000, 4), Some((837_799, 525)));
+        assert_eq!(longest_chain(1, 10, 3), Some((9, 20)));
+        assert_eq!(longest_chain(1, 2, 2), Some((1, 1)));
+        assert_eq!(longest_chain(10, 10, 2), None);
+
+        let mut c = Collatz::new();
+        let expected = (50_000..120_000)
+            .map(|n| (n, c.collatz(n)))
+            .max_by_key(|&(n, length)| (length, std::cmp::Reverse(n)));
+        for threads in 1..=5 {
+            assert_eq!(longest_chain(50_000, 120_000, threads), expected);
+        }
+        let expected = (9_999_000..10_000_000)
+            .map(|n| (n, c.collatz(n)))
+            .max_by_key(|&(n, length)| (length, std::cmp::Reverse(n)));
+        assert_eq!(longest_chain(9_999_000, 10_000_000, 3), expected);
+    }
+
+    #[test]
+    fn bounded_cache_matches_unbounded() {
+        let mut sparse = Collatz::new();
+        let mut dense = Collatz::with_capacity(1_000);
+        assert!(dense.cache.is_empty());
+        for n in 1..5_000 {
+            assert_eq!(dense.collatz(n), sparse.collatz(n));
+        }
+        assert!(dense.cache.len() < 1_000);
+        assert_eq!(Collatz::with_capacity(0).collatz(27), 112);
+        assert_eq!(Collatz::with_capacity(1).collatz(1), 1);
+    }
 }
@@ -1,55 +1,293 @@
-use num_traits::Num;
-use std::ops::Add;
+use crate::modular::mul_mod;
+use crate::number_theory::lcm;
+use crate::primes::PrimeFactorCount;
+use crate::recurrence::LinearRecurrence;
+use num::bigint::ToBigInt;
+use num::BigInt;
+use num_traits::{CheckedAdd, Num};
+use std::fmt;
 
+/// The Fibonacci numbers from `F(1)`: 1, 1, 2, 3, 5, ...
 pub struct Fib<T> {
-    a: T,
-    b: T,
-    fib_type: FibType<T>,
+    recurrence: LinearRecurrence<T>,
 }
 
-enum FibType<T> {
-    FibRegular,
-    FibLimited(T),
-}
-
-impl<T: Clone + Default + Num> Fib<T> {
+impl<T: Clone + Num> Fib<T> {
     pub fn new() -> Fib<T> {
-        let mut a: T = Default::default();
-        let mut b: T = Default::default();
-        a.set_zero();
-        b.set_one();
         Fib {
-            a,
-            b,
-            fib_type: FibType::FibRegular,
+            recurrence: LinearRecurrence::new(vec![T::one(), T::one()], vec![T::one(), T::one()]),
         }
     }
 
     pub fn limit(&mut self, limit: T) -> Fib<T> {
         Fib {
-            a: self.a.clone(),
-            b: self.b.clone(),
-            fib_type: FibType::FibLimited(limit),
+            recurrence: self.recurrence.limit(limit),
+        }
+    }
+
+    /// The terms reduced modulo `modulus`, which must leave room in `T` for
+    /// twice its value. Cannot be combined with `limit`.
+    pub fn modulo(&mut self, modulus: T) -> Fib<T> {
+        Fib {
+            recurrence: self.recurrence.modulo(modulus),
+        }
+    }
+
+    /// `F(n)` with `F(0) = 0` and `F(1) = 1`, by fast doubling in
+    /// `O(log n)` multiplications. Only terms up to `F(n)` are formed, so
+    /// this is safe for any `n` whose term fits in `T`.
+    pub fn nth_value(n: u64) -> T {
+        if n == 0 {
+            return T::zero();
+        }
+        let (a, b) = fib_pair::<T>(n / 2);
+        match n % 2 {
+            0 => a.clone() * (b.clone() + b - a),
+            _ => a.clone() * a + b.clone() * b,
+        }
+    }
+}
+
+impl<T: Clone + Num + CheckedAdd> Fib<T> {
+    /// Like `Fib::new`, but stops after the last term representable in
+    /// `T` instead of overflowing.
+    pub fn checked() -> CheckedFib<T> {
+        CheckedFib {
+            current: Some(T::one()),
+            following: Some(T::one()),
+        }
+    }
+
+    /// Like `Fib::new`, but carries on in `BigInt` once `T` overflows.
+    pub fn promoting() -> PromotingFib<T> {
+        PromotingFib {
+            small: Fib::checked(),
+            // F(-1) = 1 and F(0) = 0, so the first term follows on.
+            last_two: (T::one(), T::zero()),
+            big: None,
+        }
+    }
+}
+
+impl Fib<u64> {
+    /// `F(n) mod m`, by fast doubling.
+    pub fn nth_mod(n: u64, m: u64) -> u64 {
+        let (mut a, mut b) = (0, 1 % m);
+        for bit in (0..64 - n.leading_zeros()).rev() {
+            let wide = m as u128;
+            let doubled = ((2 * b as u128 + wide - a as u128) % wide) as u64;
+            let c = mul_mod(a, doubled, m);
+            let d = ((mul_mod(a, a, m) as u128 + mul_mod(b, b, m) as u128) % wide) as u64;
+            (a, b) = match (n >> bit) & 1 {
+                0 => (c, d),
+                _ => (d, ((c as u128 + d as u128) % wide) as u64),
+            };
         }
+        a
+    }
+}
+
+/// The period of the Fibonacci numbers modulo `m`. Built from the prime
+/// powers of `m`: `pi(p^k) = p^(k - 1) pi(p)`, and the whole period is the
+/// lcm of those.
+pub fn pisano_period(m: u64) -> u64 {
+    assert!(m > 0, "modulus must be positive");
+    PrimeFactorCount::new(m)
+        .factors
+        .iter()
+        .map(|(&p, &exp)| p.pow(exp as u32 - 1) * prime_pisano_period(p))
+        .fold(1, lcm)
+}
+
+/// `pi(p)` for a prime `p`. For `p = +-1 (mod 5)` it divides `p - 1`, and
+/// for `p = +-2 (mod 5)` it divides `2(p + 1)`, so it is the first divisor
+/// `d` of that bound with `F(d) = 0` and `F(d + 1) = 1 (mod p)`.
+fn prime_pisano_period(p: u64) -> u64 {
+    let bound = match p {
+        2 => return 3,
+        5 => return 20,
+        _ => match p % 5 {
+            1 | 4 => p - 1,
+            _ => 2 * (p + 1),
+        },
+    };
+    PrimeFactorCount::new(bound)
+        .divisors()
+        .find(|&d| Fib::nth_mod(d, p) == 0 && Fib::nth_mod(d + 1, p) == 1)
+        .unwrap()
+}
+
+/// `(F(n), F(n + 1))` using `F(2k) = F(k) (2F(k + 1) - F(k))` and
+/// `F(2k + 1) = F(k)^2 + F(k + 1)^2`.
+fn fib_pair<T: Clone + Num>(n: u64) -> (T, T) {
+    if n == 0 {
+        return (T::zero(), T::one());
+    }
+    let (a, b) = fib_pair::<T>(n / 2);
+    let c = a.clone() * (b.clone() + b.clone() - a.clone());
+    let d = a.clone() * a + b.clone() * b;
+    match n % 2 {
+        0 => (c, d),
+        _ => (d.clone(), c + d),
     }
 }
 
-impl<T: Clone + PartialOrd + Add<Output = T>> Iterator for Fib<T> {
+impl<T: Clone + Num> Default for Fib<T> {
+    fn default() -> Self {
+        Fib::new()
+    }
+}
+
+impl<T: Clone + Num + PartialOrd> Iterator for Fib<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let c = self.a.clone() + self.b.clone();
-        self.a = self.b.clone();
-        self.b = c;
-        match &self.fib_type {
-            FibType::FibRegular => Some(self.a.clone()),
-            FibType::FibLimited(limit) => {
-                if self.a < *limit {
-                    Some(self.a.clone())
-                } else {
-                    None
-                }
+        self.recurrence.next()
+    }
+}
+
+pub struct CheckedFib<T> {
+    current: Option<T>,
+    following: Option<T>,
+}
+
+impl<T: Clone + CheckedAdd> Iterator for CheckedFib<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let term = self.current.take()?;
+        self.current = self.following.take();
+        self.following = self.current.as_ref().and_then(|c| c.checked_add(&term));
+        Some(term)
+    }
+}
+
+/// A Fibonacci term that either still fits in `T` or has moved to `BigInt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term<T> {
+    Small(T),
+    Big(BigInt),
+}
+
+impl<T: ToBigInt> Term<T> {
+    pub fn to_bigint(&self) -> BigInt {
+        match self {
+            Term::Small(small) => small.to_bigint().unwrap(),
+            Term::Big(big) => big.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Term<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::Small(small) => write!(f, "{}", small),
+            Term::Big(big) => write!(f, "{}", big),
+        }
+    }
+}
+
+pub struct PromotingFib<T> {
+    small: CheckedFib<T>,
+    last_two: (T, T),
+    big: Option<(BigInt, BigInt)>,
+}
+
+impl<T: Clone + CheckedAdd + ToBigInt> Iterator for PromotingFib<T> {
+    type Item = Term<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.big.is_none() {
+            if let Some(term) = self.small.next() {
+                self.last_two = (self.last_two.1.clone(), term.clone());
+                return Some(Term::Small(term));
             }
+            let (a, b) = &self.last_two;
+            self.big = Some((a.to_bigint().unwrap(), b.to_bigint().unwrap()));
         }
+        let (a, b) = self.big.take().unwrap();
+        let c = &a + &b;
+        self.big = Some((b, c.clone()));
+        Some(Term::Big(c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_nth_values() {
+        let terms: Vec<u64> = Fib::new().take(93).collect();
+        for (i, &term) in terms.iter().enumerate() {
+            assert_eq!(Fib::<u64>::nth_value(i as u64 + 1), term);
+        }
+        assert_eq!(Fib::<u64>::nth_value(0), 0);
+        assert_eq!(Fib::<u64>::nth_value(93), 12_200_160_415_121_876_738);
+
+        assert_eq!(Fib::<BigInt>::nth_value(4_782).to_string().len(), 1_000);
+        assert_eq!(Fib::<BigInt>::nth_value(4_781).to_string().len(), 999);
+    }
+
+    #[test]
+    fn stops_before_overflow() {
+        let bytes: Vec<u8> = Fib::checked().collect();
+        assert_eq!(bytes.len(), 13);
+        assert_eq!(bytes.last(), Some(&233));
+
+        let longs: Vec<u64> = Fib::checked().collect();
+        assert_eq!(longs.len(), 93);
+        assert_eq!(longs.last(), Some(&12_200_160_415_121_876_738));
+        assert_eq!(longs, Fib::new().take(93).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn promotes_to_bigint() {
+        let terms: Vec<Term<u8>> = Fib::promoting().take(200).collect();
+        assert_eq!(terms[12], Term::Small(233));
+        assert_eq!(terms[13], Term::Big(BigInt::from(377)));
+        assert_eq!(terms[13].to_string(), "377");
+        let expected: Vec<BigInt> = Fib::new().take(200).collect();
+        let promoted: Vec<BigInt> = terms.iter().map(Term::to_bigint).collect();
+        assert_eq!(promoted, expected);
+    }
+
+    #[test]
+    fn finds_pisano_periods() {
+        let brute_force = |m: u64| {
+            let terms: Vec<u64> = Fib::new().modulo(m).take(6 * m as usize + 2).collect();
+            terms
+                .windows(2)
+                .position(|w| w == [0, 1 % m])
+                .map(|i| i as u64 + 1)
+                .unwrap()
+        };
+        for m in 1..300 {
+            assert_eq!(pisano_period(m), brute_force(m), "m = {}", m);
+        }
+        assert_eq!(pisano_period(10), 60);
+        assert_eq!(pisano_period(1_000_000_007), 2_000_000_016);
+        assert_eq!(pisano_period(1_000_000_000), 1_500_000_000);
+    }
+
+    #[test]
+    fn finds_nth_values_mod() {
+        let m = 1_000_000_007;
+        for n in 0..500 {
+            let expected = Fib::<BigInt>::nth_value(n) % BigInt::from(m);
+            assert_eq!(BigInt::from(Fib::nth_mod(n, m)), expected);
+        }
+        assert_eq!(Fib::nth_mod(10, 1), 0);
+
+        // F(2k) = F(k) (2F(k + 1) - F(k)) holds for huge k too.
+        let k = 1_000_000_000_000_000_000;
+        let (a, b) = (Fib::nth_mod(k, m), Fib::nth_mod(k + 1, m));
+        let expected = mul_mod(a, (2 * b + m - a) % m, m);
+        assert_eq!(Fib::nth_mod(2 * k, m), expected);
+        let big_m = u64::MAX - 58;
+        assert_eq!(Fib::nth_mod(300, big_m), {
+            let expected = Fib::<BigInt>::nth_value(300) % BigInt::from(big_m);
+            expected.to_string().parse::<u64>().unwrap()
+        });
     }
 }
@@ -0,0 +1,12 @@
+pub mod collatz;
+pub mod fib;
+pub mod modular;
+pub mod multiplicative;
+pub mod number_theory;
+pub mod order;
+pub mod primes;
+pub mod quadratic;
+pub mod recurrence;
+pub mod triangle;
+pub mod utils;
+pub mod zeckendorf;
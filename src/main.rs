@@ -1,26 +1,21 @@
 use std::cmp::max;
 use std::collections::HashSet;
 use std::fs;
+use std::thread;
 
 use num::pow::pow;
 use num::BigInt;
 
-mod collatz;
-mod fib;
-mod primes;
-mod triangle;
-mod utils;
-
-use collatz::Collatz;
-use fib::Fib;
-use primes::PrimeEndless;
-use primes::PrimeFactorCount;
-use primes::PrimeSieve;
-use triangle::TriangularNumber;
-use utils::factor_count;
-use utils::max_product_window;
-use utils::proper_divisors;
-use utils::Reversable;
+use euler_rust::collatz::longest_chain;
+use euler_rust::fib::Fib;
+use euler_rust::primes::PrimeEndless;
+use euler_rust::primes::PrimeFactorCount;
+use euler_rust::primes::PrimeSieve;
+use euler_rust::primes::SmallestFactorSieve;
+use euler_rust::triangle::TriangularNumber;
+use euler_rust::utils::factor_count;
+use euler_rust::utils::max_product_window;
+use euler_rust::utils::Reversable;
 
 fn main() {
     println!("p001: {}", problem001());
@@ -62,11 +57,10 @@ fn problem002() -> usize {
 }
 
 fn problem003() -> usize {
-    let num = 600_851_475_143;
-    let limit = (num as f64).sqrt() as usize + 1;
-    PrimeSieve::new(limit)
-        .filter(|p| num % p == 0)
-        .last()
+    *PrimeFactorCount::new(600_851_475_143)
+        .factors
+        .keys()
+        .max()
         .unwrap()
 }
 
@@ -80,10 +74,9 @@ fn problem004() -> usize {
 fn problem005() -> usize {
     (1..=20)
         .map(PrimeFactorCount::new)
-        .fold(PrimeFactorCount::empty(), |acc, val| {
-            acc.minimal_combine(val)
-        })
+        .fold(PrimeFactorCount::empty(), |acc, val| acc.lcm(&val))
         .to_num()
+        .unwrap()
 }
 
 fn problem006() -> usize {
@@ -93,8 +86,7 @@ fn problem006() -> usize {
 }
 
 fn problem007() -> usize {
-    let mut primes = PrimeEndless::new().pre_calc(PrimeSieve::new(105_000).collect());
-    primes.nth(10_000).unwrap()
+    PrimeEndless::new().nth(10_000).unwrap()
 }
 
 fn problem008() -> usize {
@@ -158,22 +150,16 @@ fn problem013() -> usize {
 }
 
 fn problem014() -> u128 {
-    let mut c = Collatz::new();
-    let (num, _) = (1..1_000_000).map(|i| (i, c.collatz(i))).fold(
-        (0, 0),
-        |(acc_num, acc_dist), (f_num, f_dist)| match f_dist > acc_dist {
-            true => (f_num, f_dist),
-            false => (acc_num, acc_dist),
-        },
-    );
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let (num, _) = longest_chain(1, 1_000_000, threads).unwrap();
     num
 }
 
 fn problem015() -> usize {
-    let top = PrimeFactorCount::factorial(40);
-    let b1 = PrimeFactorCount::factorial(20);
-    let b2 = PrimeFactorCount::factorial(20);
-    (top / (b1 * b2)).to_num()
+    PrimeFactorCount::binomial(40, 20)
+        .unwrap()
+        .to_num()
+        .unwrap()
 }
 
 fn problem016() -> usize {
@@ -216,11 +202,13 @@ fn problem020() -> usize {
 }
 
 fn problem021() -> usize {
-    (1..10_000)
+    let sieve = SmallestFactorSieve::new(10_000);
+    let proper_sum = |n| sieve.divisor_sum(n) - n;
+    (2..10_000)
         .filter(|&i| {
-            let div_sum = proper_divisors(i).iter().sum();
+            let div_sum = proper_sum(i);
             match div_sum < 10_000 && i != div_sum {
-                true => proper_divisors(div_sum).iter().sum::<usize>() == i,
+                true => proper_sum(div_sum) == i,
                 false => false,
             }
         })
@@ -240,8 +228,9 @@ fn problem022() -> usize {
 }
 
 fn problem023() -> usize {
+    let sieve = SmallestFactorSieve::new(28_123);
     let abundants: HashSet<usize> = (1..=28_123)
-        .filter(|&i| proper_divisors(i).iter().sum::<usize>() > i)
+        .filter(|&i| sieve.divisor_sum(i) > 2 * i)
         .collect();
     (1..=28_123)
         .filter(|&i| {
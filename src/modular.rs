@@ -0,0 +1,415 @@
+use crate::number_theory::mod_inverse;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// `a * b mod m` without overflow, through a `u128` product.
+pub fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+pub fn pow_mod(base: u64, exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = base % m;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+fn add_mod(a: u64, b: u64, m: u64) -> u64 {
+    let (sum, overflow) = a.overflowing_add(b);
+    match overflow || sum >= m {
+        true => sum.wrapping_sub(m),
+        false => sum,
+    }
+}
+
+pub(crate) fn sub_mod(a: u64, b: u64, m: u64) -> u64 {
+    match a >= b {
+        true => a - b,
+        false => m - (b - a),
+    }
+}
+
+fn inv_mod(a: u64, m: u64) -> Option<u64> {
+    mod_inverse(a as i128, m as i128).map(|inv| inv as u64)
+}
+
+/// An integer modulo the compile-time modulus `M`, always kept reduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ModInt<const M: u64> {
+    value: u64,
+}
+
+impl<const M: u64> ModInt<M> {
+    pub fn new(value: u64) -> ModInt<M> {
+        ModInt { value: value % M }
+    }
+
+    pub fn value(self) -> u64 {
+        self.value
+    }
+
+    pub fn pow(self, exp: u64) -> ModInt<M> {
+        ModInt {
+            value: pow_mod(self.value, exp, M),
+        }
+    }
+
+    /// The multiplicative inverse, or `None` if the value shares a factor
+    /// with `M`.
+    pub fn inv(self) -> Option<ModInt<M>> {
+        inv_mod(self.value, M).map(|value| ModInt { value })
+    }
+}
+
+impl<const M: u64> From<u64> for ModInt<M> {
+    fn from(value: u64) -> Self {
+        ModInt::new(value)
+    }
+}
+
+impl<const M: u64> Add for ModInt<M> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        ModInt {
+            value: add_mod(self.value, rhs.value, M),
+        }
+    }
+}
+
+impl<const M: u64> Sub for ModInt<M> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        ModInt {
+            value: sub_mod(self.value, rhs.value, M),
+        }
+    }
+}
+
+impl<const M: u64> Mul for ModInt<M> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        ModInt {
+            value: mul_mod(self.value, rhs.value, M),
+        }
+    }
+}
+
+impl<const M: u64> Neg for ModInt<M> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        ModInt {
+            value: sub_mod(0, self.value, M),
+        }
+    }
+}
+
+impl<const M: u64> AddAssign for ModInt<M> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const M: u64> SubAssign for ModInt<M> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const M: u64> MulAssign for ModInt<M> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const M: u64> fmt::Display for ModInt<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// An integer modulo a modulus chosen at runtime. Combining values with
+/// different moduli panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DynModInt {
+    value: u64,
+    modulus: u64,
+}
+
+impl DynModInt {
+    pub fn new(value: u64, modulus: u64) -> DynModInt {
+        DynModInt {
+            value: value % modulus,
+            modulus,
+        }
+    }
+
+    pub fn value(self) -> u64 {
+        self.value
+    }
+
+    pub fn modulus(self) -> u64 {
+        self.modulus
+    }
+
+    pub fn pow(self, exp: u64) -> DynModInt {
+        DynModInt {
+            value: pow_mod(self.value, exp, self.modulus),
+            ..self
+        }
+    }
+
+    pub fn inv(self) -> Option<DynModInt> {
+        inv_mod(self.value, self.modulus).map(|value| DynModInt { value, ..self })
+    }
+
+    fn same_modulus(self, rhs: DynModInt) -> u64 {
+        assert_eq!(self.modulus, rhs.modulus, "mismatched moduli");
+        self.modulus
+    }
+}
+
+impl Add for DynModInt {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let modulus = self.same_modulus(rhs);
+        DynModInt {
+            value: add_mod(self.value, rhs.value, modulus),
+            modulus,
+        }
+    }
+}
+
+impl Sub for DynModInt {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let modulus = self.same_modulus(rhs);
+        DynModInt {
+            value: sub_mod(self.value, rhs.value, modulus),
+            modulus,
+        }
+    }
+}
+
+impl Mul for DynModInt {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let modulus = self.same_modulus(rhs);
+        DynModInt {
+            value: mul_mod(self.value, rhs.value, modulus),
+            modulus,
+        }
+    }
+}
+
+impl Neg for DynModInt {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        DynModInt {
+            value: sub_mod(0, self.value, self.modulus),
+            ..self
+        }
+    }
+}
+
+impl AddAssign for DynModInt {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for DynModInt {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for DynModInt {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl fmt::Display for DynModInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+/// Montgomery form for an odd modulus `n`: values are stored as
+/// `a * 2^64 mod n`, so each product needs a multiply and a REDC instead of
+/// a 128-bit division. Convert in with `to_mont` and out with `from_mont`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Montgomery {
+    modulus: u64,
+    inv: u64,
+    r2: u64,
+}
+
+impl Montgomery {
+    pub fn new(modulus: u64) -> Montgomery {
+        assert!(modulus % 2 == 1, "Montgomery form needs an odd modulus");
+        // Newton's iteration doubles the correct low bits of n^-1 mod 2^64
+        // each step, starting from the 3 that n * n = 1 mod 8 gives us.
+        let mut inv = modulus;
+        for _ in 0..5 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(modulus.wrapping_mul(inv)));
+        }
+        let r = ((1u128 << 64) % modulus as u128) as u64;
+        Montgomery {
+            modulus,
+            inv,
+            r2: mul_mod(r, r, modulus),
+        }
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    pub fn to_mont(&self, a: u64) -> u64 {
+        self.reduce(a as u128 * self.r2 as u128)
+    }
+
+    pub fn from_mont(&self, a: u64) -> u64 {
+        self.reduce(a as u128)
+    }
+
+    pub fn one(&self) -> u64 {
+        self.to_mont(1)
+    }
+
+    pub fn add(&self, a: u64, b: u64) -> u64 {
+        add_mod(a, b, self.modulus)
+    }
+
+    pub fn sub(&self, a: u64, b: u64) -> u64 {
+        sub_mod(a, b, self.modulus)
+    }
+
+    pub fn mul(&self, a: u64, b: u64) -> u64 {
+        self.reduce(a as u128 * b as u128)
+    }
+
+    pub fn pow(&self, base: u64, exp: u64) -> u64 {
+        let mut result = self.one();
+        let mut base = base;
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// REDC: `t * 2^-64 mod n` for `t < n * 2^64`. Subtracting `m * n`,
+    /// where `m = t * n^-1 mod 2^64`, clears the low word exactly.
+    fn reduce(&self, t: u128) -> u64 {
+        let m = (t as u64).wrapping_mul(self.inv);
+        let mn = m as u128 * self.modulus as u128;
+        let (value, borrow) = ((t >> 64) as u64).overflowing_sub((mn >> 64) as u64);
+        match borrow {
+            true => value.wrapping_add(self.modulus),
+            false => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const P: u64 = 1_000_000_007;
+
+    #[test]
+    fn modint_arithmetic() {
+        let a = ModInt::<P>::new(P - 1);
+        let b = ModInt::<P>::new(5);
+        assert_eq!((a + b).value(), 4);
+        assert_eq!((b - a).value(), 6);
+        assert_eq!((a * a).value(), 1);
+        assert_eq!((-b).value(), P - 5);
+        assert_eq!(ModInt::<P>::new(0) - ModInt::new(0), ModInt::new(0));
+
+        let mut acc = ModInt::<P>::from(1);
+        for i in 1..=100 {
+            acc *= ModInt::new(i);
+        }
+        assert_eq!(acc.value(), 437_918_130);
+
+        let last_ten = ModInt::<10_000_000_000>::new(2).pow(1_000);
+        assert_eq!(last_ten.value(), 5_668_069_376);
+
+        let big = ModInt::<{ u64::MAX }>::new(u64::MAX - 1);
+        assert_eq!((big + big).value(), u64::MAX - 2);
+    }
+
+    #[test]
+    fn modint_inverses() {
+        for a in 1..1_000 {
+            let x = ModInt::<P>::new(a);
+            assert_eq!(x * x.inv().unwrap(), ModInt::new(1));
+            assert_eq!(x.inv(), Some(x.pow(P - 2)));
+        }
+        assert_eq!(ModInt::<12>::new(5).inv(), Some(ModInt::new(5)));
+        assert_eq!(ModInt::<12>::new(8).inv(), None);
+    }
+
+    #[test]
+    fn dyn_modint_arithmetic() {
+        let a = DynModInt::new(17, 23);
+        let b = DynModInt::new(9, 23);
+        assert_eq!((a + b).value(), 3);
+        assert_eq!((b - a).value(), 15);
+        assert_eq!((a * b).value(), 153 % 23);
+        assert_eq!(a.inv().map(|inv| (inv * a).value()), Some(1));
+        assert_eq!(a.pow(22).value(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "mismatched moduli")]
+    fn dyn_modint_rejects_mixed_moduli() {
+        let _ = DynModInt::new(1, 7) + DynModInt::new(1, 11);
+    }
+
+    #[test]
+    fn montgomery_matches_plain_arithmetic() {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for &modulus in &[3, P, 998_244_353, (1 << 61) - 1, u64::MAX] {
+            let mont = Montgomery::new(modulus);
+            for _ in 0..1_000 {
+                let (a, b) = (next() % modulus, next() % modulus);
+                let (am, bm) = (mont.to_mont(a), mont.to_mont(b));
+                assert_eq!(mont.from_mont(am), a);
+                assert_eq!(mont.from_mont(mont.mul(am, bm)), mul_mod(a, b, modulus));
+                assert_eq!(mont.from_mont(mont.add(am, bm)), add_mod(a, b, modulus));
+                assert_eq!(mont.from_mont(mont.sub(am, bm)), sub_mod(a, b, modulus));
+                let exp = next();
+                assert_eq!(mont.from_mont(mont.pow(am, exp)), pow_mod(a, exp, modulus));
+            }
+        }
+    }
+}
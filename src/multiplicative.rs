@@ -0,0 +1,112 @@
+use crate::primes::PrimeFactorCount;
+use crate::primes::PrimeSieve;
+
+/// Euler's totient of every `n` in `0..=limit`, with `phi[0] = 0`.
+pub fn totient_sieve(limit: usize) -> Vec<usize> {
+    let mut phi: Vec<usize> = (0..=limit).collect();
+    for p in PrimeSieve::new(limit) {
+        for m in (p..=limit).step_by(p) {
+            phi[m] -= phi[m] / p;
+        }
+    }
+    phi
+}
+
+/// The Möbius function of every `n` in `0..=limit`, with `mu[0] = 0`.
+pub fn mobius_sieve(limit: usize) -> Vec<i8> {
+    let mut mu = vec![1i8; limit + 1];
+    mu[0] = 0;
+    for p in PrimeSieve::new(limit) {
+        for m in (p..=limit).step_by(p) {
+            mu[m] = -mu[m];
+        }
+        if let Some(square) = p.checked_mul(p) {
+            for m in (square..=limit).step_by(square) {
+                mu[m] = 0;
+            }
+        }
+    }
+    mu
+}
+
+/// The number of divisors of every `n` in `0..=limit`, with `tau[0] = 0`.
+pub fn divisor_count_sieve(limit: usize) -> Vec<usize> {
+    divisor_sum_sieve(limit, 0)
+}
+
+/// `sigma_k(n)`, the sum of the `k`th powers of the divisors, of every `n`
+/// in `0..=limit`, with `sigma[0] = 0`.
+pub fn divisor_sum_sieve(limit: usize, k: u32) -> Vec<usize> {
+    let mut sigma = vec![0; limit + 1];
+    for d in 1..=limit {
+        let term = d.pow(k);
+        for m in (d..=limit).step_by(d) {
+            sigma[m] += term;
+        }
+    }
+    sigma
+}
+
+pub fn totient(pf: &PrimeFactorCount) -> usize {
+    pf.factors
+        .iter()
+        .map(|(&p, &exp)| p.pow(exp as u32 - 1) * (p - 1))
+        .product()
+}
+
+pub fn mobius(pf: &PrimeFactorCount) -> i8 {
+    match pf.factors.values().any(|&exp| exp > 1) {
+        true => 0,
+        false if pf.factors.len().is_multiple_of(2) => 1,
+        false => -1,
+    }
+}
+
+pub fn divisor_count(pf: &PrimeFactorCount) -> usize {
+    pf.divisor_count()
+}
+
+pub fn divisor_sum(pf: &PrimeFactorCount, k: u32) -> usize {
+    pf.divisor_sum(k).expect("divisor sum overflows usize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sieves_match_single_values() {
+        let limit = 2_000;
+        let phi = totient_sieve(limit);
+        let mu = mobius_sieve(limit);
+        let tau = divisor_count_sieve(limit);
+        let sigma = divisor_sum_sieve(limit, 1);
+        let sigma_2 = divisor_sum_sieve(limit, 2);
+        for n in 1..=limit {
+            let pf = PrimeFactorCount::new(n);
+            assert_eq!(phi[n], totient(&pf));
+            assert_eq!(mu[n], mobius(&pf));
+            assert_eq!(tau[n], divisor_count(&pf));
+            assert_eq!(sigma[n], divisor_sum(&pf, 1));
+            assert_eq!(sigma_2[n], divisor_sum(&pf, 2));
+        }
+    }
+
+    #[test]
+    fn computes_known_values() {
+        let phi = totient_sieve(1_000_000);
+        assert_eq!(phi[..10], [0, 1, 1, 2, 2, 4, 2, 6, 4, 6]);
+        assert_eq!(phi[2..=8].iter().sum::<usize>(), 21);
+        assert_eq!(phi[2..].iter().sum::<usize>(), 303_963_552_391);
+
+        let mu = mobius_sieve(30);
+        assert_eq!(mu[..11], [0, 1, -1, -1, 0, -1, 1, -1, 0, 0, 1]);
+        assert_eq!(mu[30], -1);
+
+        let pf = PrimeFactorCount::new(220);
+        assert_eq!(divisor_sum(&pf, 1) - 220, 284);
+        assert_eq!(divisor_count(&pf), 12);
+        assert_eq!(divisor_sum(&pf, 0), 12);
+        assert_eq!(totient(&PrimeFactorCount::new(1)), 1);
+    }
+}
@@ -0,0 +1,163 @@
+use num::{Integer, Signed};
+
+/// Greatest common divisor, always non-negative, with `gcd(0, 0) = 0`.
+pub fn gcd<T: Integer + Clone>(a: T, b: T) -> T {
+    let (mut a, mut b) = (a, b);
+    while !b.is_zero() {
+        let r = a.clone() % b.clone();
+        a = b;
+        b = r;
+    }
+    match a < T::zero() {
+        true => T::zero() - a,
+        false => a,
+    }
+}
+
+/// Least common multiple, always non-negative, with `lcm(a, 0) = 0`.
+pub fn lcm<T: Integer + Clone>(a: T, b: T) -> T {
+    if a.is_zero() || b.is_zero() {
+        return T::zero();
+    }
+    let lcm = a.clone() / gcd(a, b.clone()) * b;
+    match lcm < T::zero() {
+        true => T::zero() - lcm,
+        false => lcm,
+    }
+}
+
+/// `(g, x, y)` with `a * x + b * y = g = gcd(a, b)` and `g >= 0`.
+pub fn ext_gcd<T: Integer + Clone + Signed>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (T::one(), T::zero());
+    let (mut old_t, mut t) = (T::zero(), T::one());
+    while !r.is_zero() {
+        let q = old_r.clone() / r.clone();
+        let next_r = old_r - q.clone() * r.clone();
+        let next_s = old_s - q.clone() * s.clone();
+        let next_t = old_t - q * t.clone();
+        old_r = r;
+        r = next_r;
+        old_s = s;
+        s = next_s;
+        old_t = t;
+        t = next_t;
+    }
+    match old_r.is_negative() {
+        true => (-old_r, -old_s, -old_t),
+        false => (old_r, old_s, old_t),
+    }
+}
+
+/// The inverse of `a` modulo `m` in `0..m`, or `None` if they share a
+/// factor.
+pub fn mod_inverse<T: Integer + Clone + Signed>(a: T, m: T) -> Option<T> {
+    let m = m.abs();
+    let (g, x, _) = ext_gcd(a.mod_floor(&m), m.clone());
+    match g.is_one() {
+        true => Some(x.mod_floor(&m)),
+        false => None,
+    }
+}
+
+/// Solves `x = residues[i] (mod moduli[i])` for every `i`, returning the
+/// smallest non-negative `x` and the lcm of the moduli, or `None` if the
+/// congruences contradict each other. The moduli need not be coprime.
+/// Intermediate products reach the lcm times the largest modulus, so use
+/// `i128` or `BigInt` when that could overflow.
+pub fn crt<T: Integer + Clone + Signed>(residues: &[T], moduli: &[T]) -> Option<(T, T)> {
+    assert_eq!(residues.len(), moduli.len(), "one residue per modulus");
+    let mut x = T::zero();
+    let mut m = T::one();
+    for (a, n) in residues.iter().zip(moduli) {
+        let n = n.abs();
+        let a = a.mod_floor(&n);
+        let (g, p, _) = ext_gcd(m.clone(), n.clone());
+        let diff = a - x.clone();
+        if !(diff.clone() % g.clone()).is_zero() {
+            return None;
+        }
+        // m * k = diff (mod n) is solved by k = (diff / g) * p (mod n / g).
+        let step = n / g.clone();
+        let k = (diff / g).mod_floor(&step) * p.mod_floor(&step);
+        x = x + m.clone() * k.mod_floor(&step);
+        m = m * step;
+    }
+    Some((x, m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigInt;
+
+    #[test]
+    fn computes_gcd_and_lcm() {
+        assert_eq!(gcd(84u32, 360), 12);
+        assert_eq!(gcd(-84i64, 360), 12);
+        assert_eq!(gcd(0u8, 0), 0);
+        assert_eq!(gcd(0usize, 7), 7);
+        assert_eq!(lcm(4u64, 6), 12);
+        assert_eq!(lcm(-4i32, 6), 12);
+        assert_eq!(lcm(0i32, 6), 0);
+        assert_eq!(
+            gcd(
+                num::pow(BigInt::from(2), 100),
+                num::pow(BigInt::from(6), 40)
+            ),
+            num::pow(BigInt::from(2), 40)
+        );
+        assert_eq!((1..=20u64).fold(1, lcm), 232_792_560);
+    }
+
+    #[test]
+    fn computes_bezout_coefficients() {
+        for a in -50i64..50 {
+            for b in -50i64..50 {
+                let (g, x, y) = ext_gcd(a, b);
+                assert_eq!(g, gcd(a, b));
+                assert_eq!(a * x + b * y, g);
+            }
+        }
+    }
+
+    #[test]
+    fn computes_inverses() {
+        assert_eq!(mod_inverse(3i64, 11), Some(4));
+        assert_eq!(mod_inverse(-3i64, 11), Some(7));
+        assert_eq!(mod_inverse(6i64, 9), None);
+        let p = BigInt::from(1_000_000_007);
+        let inv = mod_inverse(BigInt::from(2), p.clone()).unwrap();
+        assert_eq!(inv, BigInt::from(500_000_004));
+    }
+
+    #[test]
+    fn solves_congruences() {
+        assert_eq!(crt(&[2i64, 3, 2], &[3, 5, 7]), Some((23, 105)));
+        assert_eq!(crt(&[3i64, 5], &[4, 6]), Some((11, 12)));
+        assert_eq!(crt(&[1i64, 2], &[4, 6]), None);
+        assert_eq!(crt::<i64>(&[], &[]), Some((0, 1)));
+        assert_eq!(crt(&[-1i64], &[10]), Some((9, 10)));
+
+        for m1 in 1i64..20 {
+            for m2 in 1i64..20 {
+                for a1 in 0..m1 {
+                    for a2 in 0..m2 {
+                        let brute = (0..m1 * m2).find(|x| x % m1 == a1 && x % m2 == a2);
+                        let expected = brute.map(|x| (x, lcm(m1, m2)));
+                        assert_eq!(crt(&[a1, a2], &[m1, m2]), expected);
+                    }
+                }
+            }
+        }
+
+        let moduli: Vec<BigInt> = [1_000_000_007u64, 998_244_353, 1_000_000_009]
+            .iter()
+            .map(|&m| BigInt::from(m))
+            .collect();
+        let x: BigInt = "123456789012345678901234".parse().unwrap();
+        let residues: Vec<BigInt> = moduli.iter().map(|m| &x % m).collect();
+        let (solution, modulus) = crt(&residues, &moduli).unwrap();
+        assert_eq!(solution, &x % &modulus);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::modular::{mul_mod, pow_mod};
+use crate::multiplicative::totient;
+use crate::number_theory::{gcd, mod_inverse};
+use crate::primes::PrimeFactorCount;
+use std::collections::HashMap;
+
+/// The smallest `k >= 1` with `a^k = 1 (mod n)`, or `None` if `a` is not
+/// coprime to `n`. Starts from `phi(n)` and divides out each prime of
+/// `phi(n)` for as long as the power stays 1.
+pub fn multiplicative_order(a: u64, n: u64) -> Option<u64> {
+    if n == 0 {
+        return None;
+    }
+    if n == 1 {
+        return Some(1);
+    }
+    if gcd(a, n) != 1 {
+        return None;
+    }
+    let phi = totient(&PrimeFactorCount::new(n as usize)) as u64;
+    let mut order = phi;
+    for (&p, &exp) in &PrimeFactorCount::new(phi as usize).factors {
+        for _ in 0..exp {
+            if pow_mod(a, order / p as u64, n) != 1 {
+                break;
+            }
+            order /= p as u64;
+        }
+    }
+    Some(order)
+}
+
+/// The smallest primitive root modulo `n`, or `None` when the group of
+/// units modulo `n` is not cyclic (anything but 1, 2, 4, `p^k`, `2p^k`).
+pub fn primitive_root(n: u64) -> Option<u64> {
+    if n <= 4 {
+        return match n {
+            0 => None,
+            1 => Some(0),
+            _ => Some(n - 1),
+        };
+    }
+    let odd = match n % 4 {
+        0 => return None,
+        2 => n / 2,
+        _ => n,
+    };
+    if PrimeFactorCount::new(odd as usize).factors.len() != 1 {
+        return None;
+    }
+    let phi = totient(&PrimeFactorCount::new(n as usize)) as u64;
+    let primes: Vec<u64> = PrimeFactorCount::new(phi as usize)
+        .factors
+        .keys()
+        .map(|&p| p as u64)
+        .collect();
+    (2..n).find(|&g| gcd(g, n) == 1 && primes.iter().all(|&p| pow_mod(g, phi / p, n) != 1))
+}
+
+/// The smallest `x >= 0` with `g^x = h (mod n)`, or `None` if there is
+/// none. Common factors of `g` and `n` are divided out first, then
+/// baby-step giant-step runs over the order of `g` in what remains.
+pub fn discrete_log(g: u64, h: u64, n: u64) -> Option<u64> {
+    if n == 1 {
+        return Some(0);
+    }
+    let (mut g, mut h, mut n) = (g % n, h % n, n);
+    let mut coefficient = 1 % n;
+    let mut offset = 0;
+    loop {
+        let d = gcd(g, n);
+        if d == 1 {
+            break;
+        }
+        if h == coefficient {
+            return Some(offset);
+        }
+        if !h.is_multiple_of(d) {
+            return None;
+        }
+        h /= d;
+        n /= d;
+        coefficient = mul_mod(coefficient, g / d, n);
+        offset += 1;
+    }
+    g %= n;
+    // Now coefficient * g^x = h with g a unit, so solve g^x = h / coefficient.
+    let target = mul_mod(h, mod_inverse(coefficient as i128, n as i128)? as u64, n);
+    baby_step_giant_step(g, target, n).map(|x| x + offset)
+}
+
+fn baby_step_giant_step(g: u64, h: u64, n: u64) -> Option<u64> {
+    let order = multiplicative_order(g, n)?;
+    let m = order.isqrt() + 1;
+    let mut baby_steps = HashMap::new();
+    let mut power = 1 % n;
+    for j in 0..m {
+        baby_steps.entry(power).or_insert(j);
+        power = mul_mod(power, g, n);
+    }
+    let giant = mod_inverse(pow_mod(g, m, n) as i128, n as i128)? as u64;
+    let mut y = h;
+    for i in 0..m {
+        if let Some(&j) = baby_steps.get(&y) {
+            return Some(i * m + j);
+        }
+        y = mul_mod(y, giant, n);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_order(a: u64, n: u64) -> Option<u64> {
+        (1..=n).find(|&k| pow_mod(a, k, n) == 1 % n)
+    }
+
+    #[test]
+    fn finds_multiplicative_orders() {
+        for n in 1..300 {
+            for a in 0..n {
+                let expected = match gcd(a, n) {
+                    1 => brute_order(a, n),
+                    _ => None,
+                };
+                assert_eq!(multiplicative_order(a, n), expected, "{} mod {}", a, n);
+            }
+        }
+        assert_eq!(multiplicative_order(10, 7), Some(6));
+        assert_eq!(multiplicative_order(10, 983), Some(982));
+        assert_eq!(multiplicative_order(2, 1_000_000_007), Some(500_000_003));
+    }
+
+    #[test]
+    fn finds_primitive_roots() {
+        for n in 1..500 {
+            let phi = totient(&PrimeFactorCount::new(n as usize)) as u64;
+            let expected = (0..n).find(|&g| gcd(g, n) == 1 && brute_order(g, n) == Some(phi));
+            assert_eq!(primitive_root(n), expected, "mod {}", n);
+        }
+        assert_eq!(primitive_root(1_000_000_007), Some(5));
+        assert_eq!(primitive_root(998_244_353), Some(3));
+    }
+
+    #[test]
+    fn finds_discrete_logs() {
+        for n in 1..50 {
+            for g in 0..n {
+                for h in 0..n {
+                    let expected = (0..=n).find(|&x| pow_mod(g, x, n) == h);
+                    assert_eq!(discrete_log(g, h, n), expected, "{}^x = {} mod {}", g, h, n);
+                }
+            }
+        }
+        let p = 1_000_000_007;
+        let x = discrete_log(5, 123_456_789, p).unwrap();
+        assert_eq!(pow_mod(5, x, p), 123_456_789);
+        assert_eq!(discrete_log(2, 3, 7), None);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::modular::{mul_mod, pow_mod, sub_mod};
+use crate::number_theory::{crt, mod_inverse};
+use crate::primes::PrimeFactorCount;
+
+/// The Legendre symbol `(a / p)` for an odd prime `p`, by Euler's
+/// criterion: 0 if `p` divides `a`, 1 for a quadratic residue, else -1.
+pub fn legendre_symbol(a: u64, p: u64) -> i8 {
+    match pow_mod(a, (p - 1) / 2, p) {
+        0 => 0,
+        1 => 1,
+        _ => -1,
+    }
+}
+
+/// The Jacobi symbol `(a / n)` for an odd positive `n`, computed by
+/// quadratic reciprocity without factorising `n`.
+pub fn jacobi_symbol(a: u64, n: u64) -> i8 {
+    assert!(n % 2 == 1, "the Jacobi symbol needs an odd modulus");
+    let (mut a, mut n) = (a % n, n);
+    let mut result = 1;
+    while a != 0 {
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result;
+            }
+        }
+        (a, n) = (n, a);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+    match n {
+        1 => result,
+        _ => 0,
+    }
+}
+
+/// The smaller square root of `a` modulo the prime `p`, or `None` if `a` is
+/// a non-residue. The other root is `p` minus this one.
+pub fn tonelli_shanks(a: u64, p: u64) -> Option<u64> {
+    let a = a % p;
+    if p == 2 || a == 0 {
+        return Some(a);
+    }
+    if legendre_symbol(a, p) != 1 {
+        return None;
+    }
+    if p % 4 == 3 {
+        return Some(smaller_root(pow_mod(a, (p + 1) / 4, p), p));
+    }
+
+    let s = (p - 1).trailing_zeros();
+    let q = (p - 1) >> s;
+    let z = (2..p).find(|&z| legendre_symbol(z, p) == -1)?;
+    let mut m = s;
+    let mut c = pow_mod(z, q, p);
+    let mut t = pow_mod(a, q, p);
+    let mut r = pow_mod(a, q.div_ceil(2), p);
+    while t != 1 {
+        let mut i = 0;
+        let mut t2 = t;
+        while t2 != 1 {
+            t2 = mul_mod(t2, t2, p);
+            i += 1;
+        }
+        let b = pow_mod(c, 1 << (m - i - 1), p);
+        m = i;
+        c = mul_mod(b, b, p);
+        t = mul_mod(t, c, p);
+        r = mul_mod(r, b, p);
+    }
+    Some(smaller_root(r, p))
+}
+
+/// As `tonelli_shanks`, using Cipolla's method: with `w = t^2 - a` a
+/// non-residue, `(t + sqrt(w))^((p + 1) / 2)` in `F_p[sqrt(w)]` is a root.
+pub fn cipolla(a: u64, p: u64) -> Option<u64> {
+    let a = a % p;
+    if p == 2 || a == 0 {
+        return Some(a);
+    }
+    if legendre_symbol(a, p) != 1 {
+        return None;
+    }
+    let (t, w) = (0..p)
+        .map(|t| (t, sub_mod(mul_mod(t, t, p), a, p)))
+        .find(|&(_, w)| legendre_symbol(w, p) == -1)?;
+
+    let mul = |(x1, y1): (u64, u64), (x2, y2): (u64, u64)| {
+        let real =
+            (mul_mod(x1, x2, p) as u128 + mul_mod(mul_mod(y1, y2, p), w, p) as u128) % p as u128;
+        let imag = (mul_mod(x1, y2, p) as u128 + mul_mod(y1, x2, p) as u128) % p as u128;
+        (real as u64, imag as u64)
+    };
+    let mut result = (1, 0);
+    let mut base = (t, 1);
+    let mut exp = p.div_ceil(2);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    Some(smaller_root(result.0, p))
+}
+
+/// Lifts a root of `x^2 = a (mod p)` to one modulo `p^k` by Newton's
+/// method, for an odd prime `p` not dividing `a`. Returns `None` if `root`
+/// is not a root modulo `p`, or if `p^k` does not fit in a `u64`.
+pub fn hensel_lift(root: u64, a: u64, p: u64, k: u32) -> Option<u64> {
+    if p == 2 || a.is_multiple_of(p) || mul_mod(root, root, p) != a % p {
+        return None;
+    }
+    let mut modulus = p;
+    let mut root = root % p;
+    for _ in 1..k {
+        modulus = modulus.checked_mul(p)?;
+        // f(r) = r^2 - a and f'(r) = 2r, reduced mod the new modulus.
+        let f = sub_mod(mul_mod(root, root, modulus), a % modulus, modulus);
+        let inv = mod_inverse(2 * root as i128, modulus as i128)? as u64;
+        root = sub_mod(root, mul_mod(f, inv, modulus), modulus);
+    }
+    Some(root)
+}
+
+/// Every `x` in `0..n` with `x^2 = a (mod n)`, in ascending order. `n` is
+/// factorised, the roots modulo each prime power found, and every
+/// combination joined with the Chinese Remainder Theorem.
+pub fn sqrt_mod(a: u64, n: u64) -> Vec<u64> {
+    let mut roots: Vec<i128> = vec![0];
+    let mut modulus: i128 = 1;
+    for (&p, &k) in &PrimeFactorCount::new(n).factors {
+        let pk = p.pow(k as u32);
+        let local = prime_power_roots(a % pk, p, k as u32);
+        roots = roots
+            .iter()
+            .flat_map(|&r| {
+                local
+                    .iter()
+                    .map(move |&s| crt(&[r, s as i128], &[modulus, pk as i128]).unwrap().0)
+            })
+            .collect();
+        modulus *= pk as i128;
+    }
+    let mut roots: Vec<u64> = roots.into_iter().map(|r| r as u64 % n).collect();
+    roots.sort_unstable();
+    roots.dedup();
+    roots
+}
+
+fn smaller_root(r: u64, p: u64) -> u64 {
+    std::cmp::min(r, p - r)
+}
+
+/// Roots of `x^2 = a (mod p^k)`. Writing `a = p^e * b` with `p` not
+/// dividing `b`, there are none unless `e` is even, and each root is
+/// `p^(e/2) * y` for a root `y` of `y^2 = b (mod p^(k - e))` taken to any of
+/// its lifts modulo `p^(k - e/2)`.
+fn prime_power_roots(a: u64, p: u64, k: u32) -> Vec<u64> {
+    let pk = p.pow(k);
+    if a == 0 {
+        return (0..pk).step_by(p.pow(k.div_ceil(2)) as usize).collect();
+    }
+    let mut e = 0;
+    let mut b = a;
+    while b.is_multiple_of(p) {
+        b /= p;
+        e += 1;
+    }
+    if e % 2 == 1 {
+        return vec![];
+    }
+    let m = p.pow(k - e);
+    let scale = p.pow(e / 2);
+    unit_roots(b % m, p, k - e)
+        .into_iter()
+        .flat_map(|y| (0..scale).map(move |s| (scale * (y + s * m)) % pk))
+        .collect()
+}
+
+/// Roots of `y^2 = b (mod p^j)` for `b` coprime to `p`.
+fn unit_roots(b: u64, p: u64, j: u32) -> Vec<u64> {
+    if j == 0 {
+        return vec![0];
+    }
+    if p != 2 {
+        let pj = p.pow(j);
+        return match tonelli_shanks(b, p).and_then(|r| hensel_lift(r, b, p, j)) {
+            Some(r) if r == pj - r => vec![r],
+            Some(r) => vec![r, pj - r],
+            None => vec![],
+        };
+    }
+    // Hensel's lemma fails for p = 2, so lift one bit at a time instead,
+    // trying both candidates above each root.
+    let mut roots = vec![1];
+    for i in 1..j {
+        let next = 1u64 << (i + 1);
+        roots = roots
+            .iter()
+            .flat_map(|&r| [r, r + (1 << i)])
+            .filter(|&c| mul_mod(c, c, next) == b % next)
+            .collect();
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primes::PrimeSieve;
+
+    fn brute_roots(a: u64, n: u64) -> Vec<u64> {
+        (0..n).filter(|&x| x * x % n == a % n).collect()
+    }
+
+    #[test]
+    fn computes_symbols() {
+        for p in PrimeSieve::new(200).skip(1).map(|p| p as u64) {
+            for a in 0..p {
+                let expected = match (a, brute_roots(a, p).is_empty()) {
+                    (0, _) => 0,
+                    (_, true) => -1,
+                    (_, false) => 1,
+                };
+                assert_eq!(legendre_symbol(a, p), expected);
+                assert_eq!(jacobi_symbol(a, p), expected);
+            }
+        }
+        for n in (1..300u64).step_by(2) {
+            for a in 0..50 {
+                let expected: i8 = PrimeFactorCount::new(n)
+                    .factors
+                    .iter()
+                    .map(|(&p, &k)| legendre_symbol(a, p).pow(k as u32))
+                    .product();
+                assert_eq!(jacobi_symbol(a, n), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_prime_square_roots() {
+        for p in PrimeSieve::new(500).map(|p| p as u64) {
+            for a in 0..p {
+                let expected = brute_roots(a, p).first().copied();
+                assert_eq!(tonelli_shanks(a, p), expected);
+                assert_eq!(cipolla(a, p), expected);
+            }
+        }
+        let p = 1_000_000_009;
+        let r = tonelli_shanks(5, p).unwrap();
+        assert_eq!(mul_mod(r, r, p), 5);
+        assert_eq!(cipolla(5, p), Some(r));
+        let p = 998_244_353;
+        assert_eq!(tonelli_shanks(3, p), None);
+        let r = cipolla(7, p).unwrap();
+        assert_eq!(mul_mod(r, r, p), 7);
+
+        // Above 2^63, t^2 - a must not be formed as t^2 + p - a.
+        let p = 18_446_744_073_709_551_557;
+        for a in 2..2_000 {
+            assert_eq!(cipolla(a, p), tonelli_shanks(a, p), "a = {}", a);
+        }
+        let r = cipolla(60, p).unwrap();
+        assert_eq!(mul_mod(r, r, p), 60);
+    }
+
+    #[test]
+    fn lifts_roots_to_prime_powers() {
+        let root = hensel_lift(3, 2, 7, 4).unwrap();
+        assert_eq!(root * root % 2_401, 2);
+        assert_eq!(root % 7, 3);
+        assert_eq!(hensel_lift(2, 2, 7, 4), None);
+        assert_eq!(hensel_lift(0, 7, 7, 2), None);
+
+        // 3^40 is above 2^63, so nothing on the way may leave u64.
+        let big = 3u64.pow(40);
+        assert!(big > 1 << 63);
+        let root = hensel_lift(1, 7, 3, 40).unwrap();
+        assert_eq!(mul_mod(root, root, big), 7);
+        assert_eq!(hensel_lift(1, 7, 3, 41), None);
+        assert_eq!(sqrt_mod(4, big), vec![2, big - 2]);
+        let roots = sqrt_mod(7, big);
+        assert_eq!(roots.len(), 2);
+        assert!(roots.iter().all(|&r| mul_mod(r, r, big) == 7));
+    }
+
+    #[test]
+    fn finds_roots_for_any_modulus() {
+        for n in 1..400 {
+            for a in 0..n {
+                assert_eq!(sqrt_mod(a, n), brute_roots(a, n), "{} mod {}", a, n);
+            }
+        }
+        let n = 1_000_000_007 * 17;
+        let roots = sqrt_mod(4, n);
+        assert_eq!(roots.len(), 4);
+        assert!(roots.iter().all(|&r| mul_mod(r, r, n) == 4));
+    }
+}
@@ -0,0 +1,284 @@
+use num_traits::Num;
+use std::collections::VecDeque;
+
+/// A constant-coefficient linear recurrence
+/// `a(n) = c[0] a(n - 1) + c[1] a(n - 2) + ... + c[k - 1] a(n - k)`,
+/// iterated from its `k` initial terms.
+#[derive(Clone)]
+pub struct LinearRecurrence<T> {
+    coefficients: Vec<T>,
+    initial: Vec<T>,
+    window: VecDeque<T>,
+    pending: usize,
+    recurrence_type: RecurrenceType<T>,
+}
+
+#[derive(Clone)]
+#[allow(clippy::enum_variant_names)]
+enum RecurrenceType<T> {
+    RecurrenceRegular,
+    RecurrenceLimited(T),
+    RecurrenceModulo(T),
+}
+
+impl<T: Clone + Num> LinearRecurrence<T> {
+    pub fn new(coefficients: Vec<T>, initial: Vec<T>) -> LinearRecurrence<T> {
+        assert!(!coefficients.is_empty(), "recurrence needs an order");
+        assert_eq!(
+            coefficients.len(),
+            initial.len(),
+            "need one initial term per coefficient"
+        );
+        LinearRecurrence {
+            window: initial.iter().cloned().collect(),
+            pending: initial.len(),
+            coefficients,
+            initial,
+            recurrence_type: RecurrenceType::RecurrenceRegular,
+        }
+    }
+
+    /// 0, 1, 1, 2, 3, 5, ...
+    pub fn fibonacci() -> LinearRecurrence<T> {
+        LinearRecurrence::new(vec![T::one(), T::one()], vec![T::zero(), T::one()])
+    }
+
+    /// 2, 1, 3, 4, 7, 11, ...
+    pub fn lucas() -> LinearRecurrence<T> {
+        let two = T::one() + T::one();
+        LinearRecurrence::new(vec![T::one(), T::one()], vec![two, T::one()])
+    }
+
+    /// 0, 1, 2, 5, 12, 29, ...
+    pub fn pell() -> LinearRecurrence<T> {
+        let two = T::one() + T::one();
+        LinearRecurrence::new(vec![two, T::one()], vec![T::zero(), T::one()])
+    }
+
+    /// 0, 0, 1, 1, 2, 4, 7, 13, ...
+    pub fn tribonacci() -> LinearRecurrence<T> {
+        LinearRecurrence::new(
+            vec![T::one(), T::one(), T::one()],
+            vec![T::zero(), T::zero(), T::one()],
+        )
+    }
+
+    /// 1, 1, 1, 2, 2, 3, 4, 5, 7, ...
+    pub fn padovan() -> LinearRecurrence<T> {
+        LinearRecurrence::new(
+            vec![T::zero(), T::one(), T::one()],
+            vec![T::one(), T::one(), T::one()],
+        )
+    }
+
+    /// Stop before the first term not below `limit`. Cannot be combined
+    /// with `modulo`.
+    pub fn limit(&mut self, limit: T) -> LinearRecurrence<T> {
+        assert!(
+            !matches!(self.recurrence_type, RecurrenceType::RecurrenceModulo(_)),
+            "a recurrence cannot be both limited and reduced"
+        );
+        LinearRecurrence {
+            coefficients: self.coefficients.clone(),
+            initial: self.initial.clone(),
+            window: self.window.clone(),
+            pending: self.pending,
+            recurrence_type: RecurrenceType::RecurrenceLimited(limit),
+        }
+    }
+
+    /// Iterate the terms reduced modulo `modulus`. Each new term is formed
+    /// from reduced terms, so `T` only needs room for `k` products of values
+    /// below `modulus`. Cannot be combined with `limit`.
+    pub fn modulo(&mut self, modulus: T) -> LinearRecurrence<T> {
+        assert!(
+            !matches!(self.recurrence_type, RecurrenceType::RecurrenceLimited(_)),
+            "a recurrence cannot be both limited and reduced"
+        );
+        LinearRecurrence {
+            coefficients: self.coefficients.clone(),
+            initial: self.initial.clone(),
+            window: self
+                .window
+                .iter()
+                .map(|a| a.clone() % modulus.clone())
+                .collect(),
+            pending: self.pending,
+            recurrence_type: RecurrenceType::RecurrenceModulo(modulus),
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// `a(n)` counted from the initial terms, whatever the iterator has
+    /// already consumed. Uses Kitamasa's method: `x^n` is reduced modulo the
+    /// characteristic polynomial by square-and-multiply, so this takes
+    /// `O(k^2 log n)` multiplications.
+    pub fn nth_term(&self, n: u64) -> T {
+        let k = self.order();
+        if n < k as u64 {
+            return self.initial[n as usize].clone();
+        }
+        let mut base = vec![T::zero(); k];
+        match k {
+            1 => base[0] = self.coefficients[0].clone(),
+            _ => base[1] = T::one(),
+        }
+        let mut result = vec![T::zero(); k];
+        result[0] = T::one();
+        let mut exp = n;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.poly_mul(&result, &base);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = self.poly_mul(&base, &base);
+            }
+        }
+        result
+            .into_iter()
+            .zip(self.initial.iter())
+            .fold(T::zero(), |acc, (r, a)| acc + r * a.clone())
+    }
+
+    /// The product of two polynomials of degree below `k`, reduced modulo
+    /// the characteristic polynomial.
+    fn poly_mul(&self, a: &[T], b: &[T]) -> Vec<T> {
+        let k = self.order();
+        let mut product = vec![T::zero(); 2 * k - 1];
+        for (i, x) in a.iter().enumerate().filter(|(_, x)| !x.is_zero()) {
+            for (j, y) in b.iter().enumerate() {
+                product[i + j] = product[i + j].clone() + x.clone() * y.clone();
+            }
+        }
+        for d in (k..2 * k - 1).rev() {
+            let top = std::mem::replace(&mut product[d], T::zero());
+            if top.is_zero() {
+                continue;
+            }
+            for (j, c) in self.coefficients.iter().enumerate() {
+                product[d - 1 - j] = product[d - 1 - j].clone() + top.clone() * c.clone();
+            }
+        }
+        product.truncate(k);
+        product
+    }
+
+    /// The next term. The initial terms come first, after which each call
+    /// forms exactly one new term, so nothing past the yielded term is
+    /// computed.
+    fn advance(&mut self) -> T {
+        if self.pending > 0 {
+            self.pending -= 1;
+            return self.window[self.order() - 1 - self.pending].clone();
+        }
+        let next = self.coefficients.iter().zip(self.window.iter().rev()).fold(
+            T::zero(),
+            |acc, (c, a)| match c.is_one() {
+                true => acc + a.clone(),
+                false => acc + c.clone() * a.clone(),
+            },
+        );
+        let next = match &self.recurrence_type {
+            RecurrenceType::RecurrenceModulo(modulus) => next % modulus.clone(),
+            _ => next,
+        };
+        self.window.pop_front();
+        self.window.push_back(next.clone());
+        next
+    }
+}
+
+impl<T: Clone + Num + PartialOrd> Iterator for LinearRecurrence<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let term = self.advance();
+        match &self.recurrence_type {
+            RecurrenceType::RecurrenceRegular | RecurrenceType::RecurrenceModulo(_) => Some(term),
+            RecurrenceType::RecurrenceLimited(limit) => {
+                if term < *limit {
+                    Some(term)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fib::Fib;
+    use num::BigInt;
+
+    #[test]
+    fn iterates_named_sequences() {
+        let take = |r: LinearRecurrence<u64>| r.take(10).collect::<Vec<_>>();
+        assert_eq!(
+            take(LinearRecurrence::fibonacci()),
+            vec![0, 1, 1, 2, 3, 5, 8, 13, 21, 34]
+        );
+        assert_eq!(
+            take(LinearRecurrence::lucas()),
+            vec![2, 1, 3, 4, 7, 11, 18, 29, 47, 76]
+        );
+        assert_eq!(
+            take(LinearRecurrence::pell()),
+            vec![0, 1, 2, 5, 12, 29, 70, 169, 408, 985]
+        );
+        assert_eq!(
+            take(LinearRecurrence::tribonacci()),
+            vec![0, 0, 1, 1, 2, 4, 7, 13, 24, 44]
+        );
+        assert_eq!(
+            take(LinearRecurrence::padovan()),
+            vec![1, 1, 1, 2, 2, 3, 4, 5, 7, 9]
+        );
+
+        let odds: Vec<i64> = LinearRecurrence::new(vec![2, -1], vec![1, 3])
+            .limit(12)
+            .collect();
+        assert_eq!(odds, vec![1, 3, 5, 7, 9, 11]);
+        let pell_mod: Vec<u64> = LinearRecurrence::pell().modulo(7).take(10).collect();
+        assert_eq!(pell_mod, vec![0, 1, 2, 5, 5, 1, 0, 1, 2, 5]);
+        let doubling: Vec<u32> = LinearRecurrence::new(vec![2], vec![1]).take(5).collect();
+        assert_eq!(doubling, vec![1, 2, 4, 8, 16]);
+    }
+
+    #[test]
+    #[should_panic(expected = "both limited and reduced")]
+    fn should_reject_limit_with_modulo() {
+        LinearRecurrence::<u64>::fibonacci().limit(100).modulo(7);
+    }
+
+    #[test]
+    fn finds_nth_terms() {
+        let recurrences: Vec<LinearRecurrence<u64>> = vec![
+            LinearRecurrence::fibonacci(),
+            LinearRecurrence::lucas(),
+            LinearRecurrence::pell(),
+            LinearRecurrence::tribonacci(),
+            LinearRecurrence::padovan(),
+            LinearRecurrence::new(vec![3, 0, 2, 1], vec![1, 0, 4, 2]),
+            LinearRecurrence::new(vec![3], vec![2]),
+        ];
+        for recurrence in recurrences {
+            for (n, term) in recurrence.clone().take(30).enumerate() {
+                assert_eq!(recurrence.nth_term(n as u64), term);
+            }
+        }
+
+        let fib = LinearRecurrence::<BigInt>::fibonacci();
+        assert_eq!(fib.nth_term(1_000), Fib::<BigInt>::nth_value(1_000));
+        let lucas = LinearRecurrence::<BigInt>::lucas();
+        assert_eq!(
+            lucas.nth_term(500),
+            Fib::<BigInt>::nth_value(499) + Fib::<BigInt>::nth_value(501)
+        );
+    }
+}
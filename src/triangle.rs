@@ -9,6 +9,12 @@ impl TriangularNumber {
     }
 }
 
+impl Default for TriangularNumber {
+    fn default() -> Self {
+        TriangularNumber::new()
+    }
+}
+
 impl Iterator for TriangularNumber {
     type Item = usize;
 
@@ -1,3 +1,4 @@
+use crate::primes::PrimeFactorCount;
 use std::cmp::max;
 
 pub trait Reversable {
@@ -18,6 +19,7 @@ impl Reversable for usize {
     }
 }
 
+#[allow(clippy::needless_range_loop)]
 pub fn max_product_window(nums: Vec<Vec<usize>>, window: usize) -> usize {
     let mut ans = 0;
     let cols = nums.len();
@@ -67,24 +69,20 @@ pub fn max_product_window(nums: Vec<Vec<usize>>, window: usize) -> usize {
 }
 
 pub fn factor_count(n: usize) -> usize {
-    (1..=((n as f64).sqrt() as usize))
-        .filter(|&d| n % d == 0)
-        .map(|d| if d * d == n { 1 } else { 2 })
-        .sum()
+    match n {
+        0 => 0,
+        _ => PrimeFactorCount::new(n).divisor_count(),
+    }
 }
 
 pub fn proper_divisors(n: usize) -> Vec<usize> {
-    let mut divisors: Vec<usize> = vec![];
-    for d in 1..=((n as f64).sqrt() as usize) {
-        if n % d == 0 {
-            divisors.push(d);
-            let other = n / d;
-            if other != d && other != n {
-                divisors.push(other);
-            }
-        }
+    match n {
+        0 => vec![],
+        _ => PrimeFactorCount::new(n)
+            .divisors()
+            .filter(|&d| d != n)
+            .collect(),
     }
-    divisors
 }
 
 #[cfg(test)]
@@ -107,5 +105,14 @@ mod tests {
         let mut result = proper_divisors(284);
         result.sort();
         assert_eq!(result, vec![1, 2, 4, 71, 142]);
+
+        assert_eq!(proper_divisors(1), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn counts_factors() {
+        assert_eq!(factor_count(1), 1);
+        assert_eq!(factor_count(28), 6);
+        assert_eq!(factor_count(76_576_500), 576);
     }
 }
@@ -0,0 +1,150 @@
+use crate::fib::Fib;
+
+/// The distinct Fibonacci numbers 1, 2, 3, 5, 8, ... that fit in a `u64`.
+/// Digit `i` of the Fibonacci base stands for `basis()[i]`.
+fn basis() -> Vec<u64> {
+    Fib::checked().skip(1).collect()
+}
+
+/// The unique way of writing `n` as a sum of non-consecutive Fibonacci
+/// numbers, largest first. Greedy: always take the largest that fits.
+pub fn zeckendorf(n: u64) -> Vec<u64> {
+    let mut terms = Vec::new();
+    let mut rest = n;
+    for &f in basis().iter().rev() {
+        if f <= rest {
+            terms.push(f);
+            rest -= f;
+        }
+    }
+    terms
+}
+
+/// The sum of a Zeckendorf representation, or `None` if `terms` are not
+/// distinct, non-consecutive Fibonacci numbers in decreasing order.
+pub fn from_zeckendorf(terms: &[u64]) -> Option<u64> {
+    let basis = basis();
+    let mut bits = 0u128;
+    for pair in terms.windows(2) {
+        if pair[0] <= pair[1] {
+            return None;
+        }
+    }
+    for term in terms {
+        bits |= 1 << basis.binary_search(term).ok()?;
+    }
+    from_fibonacci_base(bits)
+}
+
+/// `n` in the Fibonacci base: bit `i` is set when `basis()[i]` is one of
+/// its Zeckendorf terms, so no two adjacent bits are set.
+pub fn to_fibonacci_base(n: u64) -> u128 {
+    let basis = basis();
+    zeckendorf(n)
+        .iter()
+        .fold(0, |bits, f| bits | 1 << basis.binary_search(f).unwrap())
+}
+
+/// The value of a Fibonacci-base number, or `None` if two adjacent bits
+/// are set or the value does not fit in a `u64`.
+pub fn from_fibonacci_base(bits: u128) -> Option<u64> {
+    if bits & (bits >> 1) != 0 {
+        return None;
+    }
+    let basis = basis();
+    if bits >> basis.len() != 0 {
+        return None;
+    }
+    basis
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| bits >> i & 1 == 1)
+        .try_fold(0u64, |acc, (_, &f)| acc.checked_add(f))
+}
+
+/// The number of Zeckendorf terms of every `n` in `0..limit`. Each `n`
+/// has one more term than `n` minus its largest Fibonacci number.
+pub fn zeckendorf_term_counts(limit: usize) -> Vec<u8> {
+    let mut counts = vec![0u8; limit];
+    let mut largest = 1;
+    let mut next = 2;
+    for n in 1..limit {
+        if n == next {
+            let following = largest + next;
+            largest = next;
+            next = following;
+        }
+        counts[n] = counts[n - largest] + 1;
+    }
+    counts
+}
+
+/// The total number of Zeckendorf terms over `low..high`.
+pub fn zeckendorf_term_sum(low: u64, high: u64) -> u128 {
+    assert!(low <= high, "range must be ordered");
+    let basis = basis();
+    // block[i] is the term count summed over 0..basis[i]: the numbers in
+    // basis[i]..basis[i + 1] are basis[i] plus each of 0..basis[i - 1].
+    let mut block = vec![0u128, 1];
+    for i in 1..basis.len() - 1 {
+        block.push(block[i] + basis[i - 1] as u128 + block[i - 1]);
+    }
+    let below = |n: u64| {
+        let mut rest = n;
+        let mut total = 0;
+        let mut taken = 0;
+        for (i, &f) in basis.iter().enumerate().rev() {
+            if f <= rest {
+                total += block[i] + taken * f as u128;
+                taken += 1;
+                rest -= f;
+            }
+        }
+        total
+    };
+    below(high) - below(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_representations() {
+        assert_eq!(zeckendorf(0), Vec::<u64>::new());
+        assert_eq!(zeckendorf(100), vec![89, 8, 3]);
+        assert_eq!(zeckendorf(64), vec![55, 8, 1]);
+        assert_eq!(to_fibonacci_base(100), 0b1000010100);
+        assert_eq!(from_fibonacci_base(0b1000010100), Some(100));
+        assert_eq!(from_fibonacci_base(0b110), None);
+        assert_eq!(from_zeckendorf(&[89, 8, 3]), Some(100));
+        assert_eq!(from_zeckendorf(&[8, 89]), None);
+        assert_eq!(from_zeckendorf(&[8, 5]), None);
+        assert_eq!(from_zeckendorf(&[7]), None);
+
+        for n in (0..10_000).chain([u64::MAX - 1, u64::MAX]) {
+            let terms = zeckendorf(n);
+            assert_eq!(from_zeckendorf(&terms), Some(n));
+            assert_eq!(from_fibonacci_base(to_fibonacci_base(n)), Some(n));
+        }
+    }
+
+    #[test]
+    fn sums_term_counts() {
+        let counts = zeckendorf_term_counts(10_000);
+        for (n, &count) in counts.iter().enumerate() {
+            assert_eq!(count as usize, zeckendorf(n as u64).len());
+        }
+        let prefix = |n: usize| counts[..n].iter().map(|&c| c as u128).sum::<u128>();
+        for (low, high) in [(0, 0), (0, 1), (0, 10), (7, 1_000), (233, 9_999)] {
+            assert_eq!(
+                zeckendorf_term_sum(low as u64, high as u64),
+                prefix(high) - prefix(low)
+            );
+        }
+        assert_eq!(
+            zeckendorf_term_sum(1, 100_000_000_000_000_000),
+            2_252_639_041_804_718_029
+        );
+    }
+}